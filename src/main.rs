@@ -1,6 +1,7 @@
 #![forbid(unsafe_code)]
 
-use log::error;
+use image::ColorType;
+use log::{error, info};
 use pixels::{Error, Pixels, SurfaceTexture};
 use winit::dpi::LogicalSize;
 use winit::event::{Event, VirtualKeyCode};
@@ -9,16 +10,64 @@ use winit::window::WindowBuilder;
 use winit_input_helper::WinitInputHelper;
 use num_complex::Complex64;
 use rayon::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::thread::JoinHandle;
 
-const RESOLUTION: u32 = 800;
+// Window size at startup. The sampling grid tracks the window's actual size
+// from here on, via `MandelbrotSet::resize`.
+const INITIAL_RESOLUTION: u32 = 800;
 
+// Interior points (orbit never escaped) are tagged with this value so
+// `color_function` can give them a dedicated color instead of a palette hue.
+const INTERIOR: f32 = -1.0;
+
+// Progressive refinement passes, coarsest first: each pass fills an NxN block
+// of pixels with a single sample before the next pass halves the block size.
+const REFINEMENT_STEPS: [usize; 4] = [8, 4, 2, 1];
+
+// Below this view width/height, neighboring pixels round to the same `f64`
+// coordinate under direct iteration, so switch to perturbation theory.
+const PERTURBATION_THRESHOLD: f64 = 1e-13;
+
+// Default width of the PNG produced by the `P` export keybind, independent
+// of the window/sampling resolution; height follows the view's aspect
+// ratio. Overridable with a `--export-resolution <pixels>` command-line
+// argument.
+const EXPORT_RESOLUTION: u32 = 2000;
+
+/// A single pixel's escape result: the continuous value used for linear
+/// coloring alongside the raw iteration count histogram coloring needs.
+#[derive(Clone, Copy)]
+struct Sample {
+    mu: f32,
+    iterations: u32,
+}
 
 struct MandelbrotSet {
-    set: [u8; ((RESOLUTION*RESOLUTION) as usize)],
+    /// Buffer displayed by `draw`. Only ever touched by the main thread.
+    front: Vec<Sample>,
+    /// Buffer the background render thread writes into.
+    back: Arc<Mutex<Vec<Sample>>>,
+    /// Cache of `front` remapped through a histogram-equalized CDF, rebuilt
+    /// whenever a fresh pass lands while `use_histogram` is set.
+    front_histogram: Vec<f32>,
+    /// Toggles between linear (the continuous escape value `mu`) and
+    /// histogram-equalized color mapping.
+    use_histogram: bool,
+    /// Set by the render thread after each refinement pass completes, so the
+    /// main thread knows to swap `back` into `front` and redraw.
+    pass_ready: Arc<AtomicBool>,
+    /// Set to request the in-flight render thread abort early.
+    cancel: Arc<AtomicBool>,
+    render_handle: Option<JoinHandle<()>>,
+    width: u32,
+    height: u32,
     re_limits: [f64; 2],
     im_limits: [f64; 2],
     max_iterations: u32,
-    color_function: fn(u8) -> [u8; 3]
+    color_function: fn(f32) -> [u8; 3]
 }
 
 fn hsv_to_rgb(hsv: [u8; 3]) -> [u8; 3] {
@@ -37,10 +86,16 @@ fn hsv_to_rgb(hsv: [u8; 3]) -> [u8; 3] {
 }
 
 impl MandelbrotSet {
-    pub fn normalize(iterations: u32, max_iterations: u32) -> u8 {
-        ((iterations as f32) / (max_iterations as f32) * 255.0).round() as u8
+    /// Converts a raw escape iteration/`z` pair into a continuous escape value
+    /// in `[0, max_iterations]`, or `INTERIOR` if the point never escaped.
+    pub fn normalize(iterations: u32, z: Complex64, max_iterations: u32) -> f32 {
+        if iterations > max_iterations {
+            return INTERIOR;
+        }
+        let mu = (iterations as f64) + 1.0 - (z.norm().ln().ln() / std::f64::consts::LN_2);
+        mu.clamp(0.0, max_iterations as f64) as f32
     }
-    pub fn mandelbrot(re: f64, im: f64, max_iterations: u32) -> u32 {
+    pub fn mandelbrot(re: f64, im: f64, max_iterations: u32) -> (u32, Complex64) {
         let mut n = 0;
         let c = Complex64::new(re, im);
         let mut z = Complex64::new(0.0, 0.0);
@@ -48,38 +103,342 @@ impl MandelbrotSet {
             z = z.powu(2) + c;
             n += 1;
         }
-        n
+        (n, z)
+    }
+    /// Computes the reference orbit `Z_0, Z_1, ...` for a single point `c0`
+    /// in `f64`, from `Z_0 = 0` through at most `Z_max_iterations`, stopping
+    /// early at the first term that escapes.
+    fn reference_orbit(c0: Complex64, max_iterations: u32) -> Vec<Complex64> {
+        let mut orbit = Vec::with_capacity(max_iterations as usize + 1);
+        let mut z = Complex64::new(0.0, 0.0);
+        orbit.push(z);
+        for _ in 0..max_iterations {
+            if z.norm_sqr() >= 4.0 {
+                break;
+            }
+            z = z.powu(2) + c0;
+            orbit.push(z);
+        }
+        orbit
+    }
+    /// Computes a fresh reference orbit rooted at `c` itself (i.e. with
+    /// `delta` identically zero), from iteration 0 up to `max_iterations`,
+    /// and reports its escape count directly. Used to rebase a pixel whose
+    /// reference orbit has gone stale; this amounts to direct `f64`
+    /// iteration of `c`. The fresh orbit starts at `Z_0 = 0`, so its own
+    /// escape index is the true count, not an offset from the stale
+    /// reference's progress.
+    fn rebase(c: Complex64, max_iterations: u32) -> (u32, Complex64) {
+        let fresh_orbit = MandelbrotSet::reference_orbit(c, max_iterations);
+        let z = *fresh_orbit.last().unwrap();
+        if z.norm_sqr() >= 4.0 {
+            (fresh_orbit.len() as u32 - 1, z)
+        } else {
+            (max_iterations + 1, z)
+        }
+    }
+    /// Iterates the delta recurrence `delta_{n+1} = 2*Z_n*delta_n + delta_n^2
+    /// + delta_c` against a precomputed reference orbit rooted at `c0`,
+    /// testing escape on `|Z_n + delta_n| > 2`. The reference orbit and the
+    /// per-pixel deltas are both computed in `f64`. If the reference turns out to be too short, or `delta` grows
+    /// so large relative to `Z_n + delta_n` that the reference has clearly
+    /// diverged from the true orbit (a "glitch"), the pixel is rebased onto
+    /// a fresh reference orbit rooted at itself.
+    fn mandelbrot_perturbation(c: Complex64, orbit: &[Complex64], c0: Complex64, max_iterations: u32) -> (u32, Complex64) {
+        let delta_c = c - c0;
+        let mut delta = Complex64::new(0.0, 0.0);
+        let mut n: u32 = 0;
+        while (n as usize) < orbit.len() - 1 && n <= max_iterations {
+            let z_n = orbit[n as usize];
+            let z = z_n + delta;
+            if z.norm() < delta.norm() * 1e-6 {
+                return MandelbrotSet::rebase(c, max_iterations);
+            }
+            if z.norm_sqr() >= 4.0 {
+                return (n, z);
+            }
+            delta = delta * 2.0 * z_n + delta * delta + delta_c;
+            n += 1;
+        }
+        MandelbrotSet::rebase(c, max_iterations)
+    }
+    /// Renders one refinement pass into a freshly allocated buffer, filling
+    /// each `block`x`block` square with a single sample. Horizontal stripes
+    /// of `block` rows are handed to the rayon thread pool so the pass
+    /// itself is parallel. Computes into its own buffer rather than `back`
+    /// directly so the caller only needs to hold the mutex for the brief
+    /// moment it takes to publish the finished pass, not for the whole
+    /// (potentially full-resolution) computation.
+    fn render_pass(
+        re_limits: [f64; 2],
+        im_limits: [f64; 2],
+        max_iterations: u32,
+        width: usize,
+        height: usize,
+        block: usize,
+        cancel: &AtomicBool,
+    ) -> Vec<Sample> {
+        let resolution = width;
+        let re_range = re_limits[1] - re_limits[0];
+        let im_range = im_limits[1] - im_limits[0];
+        let m_re = re_range / (width as f64);
+        let m_im = im_range / (height as f64);
+        let re0 = re_limits[0];
+        let im0 = im_limits[0];
+
+        // Below the precision threshold, neighboring pixels round to the
+        // same f64 coordinate under direct iteration, so compute a single
+        // reference orbit and iterate deltas from it instead.
+        let orbit = if re_range.abs() < PERTURBATION_THRESHOLD || im_range.abs() < PERTURBATION_THRESHOLD {
+            let c0 = Complex64::new(re0 + re_range / 2.0, im0 + im_range / 2.0);
+            Some((c0, MandelbrotSet::reference_orbit(c0, max_iterations)))
+        } else {
+            None
+        };
+
+        let mut pass = vec![Sample { mu: 0.0, iterations: 0 }; width * height];
+        pass.par_chunks_mut(block * resolution)
+            .enumerate()
+            .for_each(|(stripe, rows)| {
+                if cancel.load(Ordering::Relaxed) {
+                    return;
+                }
+                let y0 = stripe * block;
+                let stripe_height = rows.len() / resolution;
+                for x0 in (0..resolution).step_by(block) {
+                    let re = re0 + m_re * (x0 as f64);
+                    let im = im0 + m_im * (y0 as f64);
+                    let (n, z) = match &orbit {
+                        Some((c0, orbit)) => MandelbrotSet::mandelbrot_perturbation(Complex64::new(re, im), orbit, *c0, max_iterations),
+                        None => MandelbrotSet::mandelbrot(re, im, max_iterations),
+                    };
+                    let sample = Sample { mu: MandelbrotSet::normalize(n, z, max_iterations), iterations: n };
+                    for by in 0..stripe_height {
+                        for bx in 0..block.min(resolution - x0) {
+                            rows[by * resolution + x0 + bx] = sample;
+                        }
+                    }
+                }
+            });
+        pass
+    }
+    /// Builds a histogram-equalized remapping of raw iteration counts: each
+    /// pixel's color index becomes the fraction of pixels that escaped in
+    /// fewer iterations (its CDF rank), scaled back into `[0, max_iterations]`
+    /// so it can be fed straight into `color_function`. Spreads the palette
+    /// over however much detail is actually present in the current view,
+    /// instead of wasting most of it on the narrow low-iteration band linear
+    /// mapping favors.
+    fn build_histogram_mapping(samples: &[Sample], max_iterations: u32) -> Vec<f32> {
+        // Interior points never escaped, so they carry no iteration-count
+        // signal; folding them into the histogram would skew the CDF of the
+        // escaped pixels the equalization is actually meant to spread out.
+        let mut counts = vec![0u32; (max_iterations + 1) as usize];
+        let mut total = 0u32;
+        for sample in samples {
+            if sample.iterations <= max_iterations {
+                counts[sample.iterations as usize] += 1;
+                total += 1;
+            }
+        }
+        let total = total.max(1) as f32;
+        let mut cdf = vec![0f32; counts.len()];
+        let mut running = 0u32;
+        for (count, rank) in counts.iter().zip(cdf.iter_mut()) {
+            *rank = running as f32 / total;
+            running += count;
+        }
+        samples
+            .iter()
+            .map(|sample| {
+                if sample.iterations > max_iterations {
+                    INTERIOR
+                } else {
+                    cdf[sample.iterations as usize] * max_iterations as f32
+                }
+            })
+            .collect()
+    }
+    /// Cancels any in-flight render and starts a new one in the background,
+    /// progressively refining `back` from a coarse block-filled pass down to
+    /// full resolution. `poll_render` picks up each completed pass.
+    pub fn start_render(self: &mut Self) {
+        self.cancel.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.render_handle.take() {
+            let _ = handle.join();
+        }
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.cancel = cancel.clone();
+
+        let back = self.back.clone();
+        let pass_ready = self.pass_ready.clone();
+        let re_limits = self.re_limits;
+        let im_limits = self.im_limits;
+        let max_iterations = self.max_iterations;
+        let width = self.width as usize;
+        let height = self.height as usize;
+
+        self.render_handle = Some(thread::spawn(move || {
+            for &block in REFINEMENT_STEPS.iter() {
+                if cancel.load(Ordering::Relaxed) {
+                    return;
+                }
+                let pass = MandelbrotSet::render_pass(re_limits, im_limits, max_iterations, width, height, block, &cancel);
+                if cancel.load(Ordering::Relaxed) {
+                    return;
+                }
+                *back.lock().unwrap() = pass;
+                pass_ready.store(true, Ordering::Relaxed);
+            }
+        }));
+    }
+    /// Swaps a freshly completed pass into `front` for `draw`. Returns
+    /// whether a swap happened, so the caller knows to request a redraw.
+    pub fn poll_render(self: &mut Self) -> bool {
+        if !self.pass_ready.swap(false, Ordering::Relaxed) {
+            return false;
+        }
+        let mut back = self.back.lock().unwrap();
+        std::mem::swap(&mut self.front, &mut back);
+        drop(back);
+        if self.use_histogram {
+            self.front_histogram = MandelbrotSet::build_histogram_mapping(&self.front, self.max_iterations);
+        }
+        true
+    }
+    /// Toggles histogram-equalized coloring on or off, rebuilding the cached
+    /// mapping immediately if it was just turned on.
+    pub fn toggle_histogram(self: &mut Self) {
+        self.use_histogram = !self.use_histogram;
+        if self.use_histogram {
+            self.front_histogram = MandelbrotSet::build_histogram_mapping(&self.front, self.max_iterations);
+        }
+    }
+    pub fn new(width: u32, height: u32) -> Self {
+        let pixel_count = (width * height) as usize;
+        let blank = Sample { mu: 0.0, iterations: 0 };
+        let re_limits = [-2.0, 2.0];
+        let im_range = (re_limits[1] - re_limits[0]) * (height as f64) / (width as f64);
+        Self {
+            front: vec![blank; pixel_count],
+            back: Arc::new(Mutex::new(vec![blank; pixel_count])),
+            front_histogram: vec![0.0; pixel_count],
+            use_histogram: false,
+            pass_ready: Arc::new(AtomicBool::new(false)),
+            cancel: Arc::new(AtomicBool::new(false)),
+            render_handle: None,
+            width,
+            height,
+            re_limits,
+            im_limits: [-im_range / 2.0, im_range / 2.0],
+            max_iterations: 255,
+            color_function: |mu: f32| {
+                let c = (mu.max(0.0) * 255.0) as u8;
+                [c, c, c]
+            }
+        }
     }
-    pub fn calculate(self: &mut Self) {
+    /// Reallocates the sampling grid to `width`x`height` and rescales
+    /// `im_limits` to match the new aspect ratio (keeping `re_limits` and the
+    /// view center fixed), so a non-square window doesn't stretch the image.
+    /// Triggers a fresh render, since the old buffers no longer apply.
+    pub fn resize(self: &mut Self, width: u32, height: u32) {
+        if width == self.width && height == self.height {
+            return;
+        }
+        let re_range = self.re_limits[1] - self.re_limits[0];
+        let im_center = (self.im_limits[0] + self.im_limits[1]) / 2.0;
+        let im_range = re_range * (height as f64) / (width as f64);
+        self.im_limits = [im_center - im_range / 2.0, im_center + im_range / 2.0];
+
+        self.width = width;
+        self.height = height;
+        let pixel_count = (width * height) as usize;
+        let blank = Sample { mu: 0.0, iterations: 0 };
+        self.front = vec![blank; pixel_count];
+        self.back = Arc::new(Mutex::new(vec![blank; pixel_count]));
+        self.front_histogram = vec![0.0; pixel_count];
+        self.start_render();
+    }
+    /// Converts a window pixel coordinate to the complex-plane point it
+    /// displays, given the window's current size. Matches the mapping
+    /// `render_pass`/`draw` use, so the point under the cursor is the same
+    /// point wheel-zoom keeps fixed.
+    pub fn pixel_to_complex(self: &Self, px: f64, py: f64, window_width: f64, window_height: f64) -> Complex64 {
         let re_range = self.re_limits[1] - self.re_limits[0];
         let im_range = self.im_limits[1] - self.im_limits[0];
-        let m_re = re_range / (RESOLUTION as f64);
-        let m_im = im_range / (RESOLUTION as f64);
+        let re = self.re_limits[0] + re_range * (px / window_width);
+        let im = self.im_limits[0] + im_range * (py / window_height);
+        Complex64::new(re, im)
+    }
+    /// Renders the current view into an RGB buffer `width` pixels wide, at a
+    /// sampling grid decoupled from the display resolution, and writes it to
+    /// `path` as a PNG. The height is derived from `width` and the view's
+    /// aspect ratio (the same way `resize` derives `im_limits` from the
+    /// window's), so the exported image isn't stretched on a non-square
+    /// view. Mirrors `render_pass` (falling back to perturbation theory
+    /// below `PERTURBATION_THRESHOLD`) and `draw` (applying
+    /// histogram-equalized coloring when `use_histogram` is set), so the
+    /// export matches what's on screen rather than always using direct
+    /// iteration and linear coloring.
+    pub fn export_png(self: &Self, width: u32, path: &str) -> image::ImageResult<()> {
+        let re_range = self.re_limits[1] - self.re_limits[0];
+        let im_range = self.im_limits[1] - self.im_limits[0];
+        let height = ((width as f64) * im_range / re_range).round() as u32;
+        let m_re = re_range / (width as f64);
+        let m_im = im_range / (height as f64);
         let re0 = self.re_limits[0];
         let im0 = self.im_limits[0];
         let max_iterations = self.max_iterations;
-        self.set.par_iter_mut().enumerate().for_each(|(i, c)|{
-            let x = i % RESOLUTION as usize;
-            let y = i / RESOLUTION as usize;
+        let color_function = self.color_function;
+
+        let orbit = if re_range.abs() < PERTURBATION_THRESHOLD || im_range.abs() < PERTURBATION_THRESHOLD {
+            let c0 = Complex64::new(re0 + re_range / 2.0, im0 + im_range / 2.0);
+            Some((c0, MandelbrotSet::reference_orbit(c0, max_iterations)))
+        } else {
+            None
+        };
+
+        let mut samples = vec![Sample { mu: 0.0, iterations: 0 }; (width * height) as usize];
+        samples.par_iter_mut().enumerate().for_each(|(i, sample)| {
+            let x = i % width as usize;
+            let y = i / width as usize;
             let re = re0 + m_re * (x as f64);
             let im = im0 + m_im * (y as f64);
-            *c = MandelbrotSet::normalize(MandelbrotSet::mandelbrot(re, im, max_iterations), max_iterations);
+            let (n, z) = match &orbit {
+                Some((c0, orbit)) => MandelbrotSet::mandelbrot_perturbation(Complex64::new(re, im), orbit, *c0, max_iterations),
+                None => MandelbrotSet::mandelbrot(re, im, max_iterations),
+            };
+            *sample = Sample { mu: MandelbrotSet::normalize(n, z, max_iterations), iterations: n };
         });
+
+        let histogram = if self.use_histogram {
+            Some(MandelbrotSet::build_histogram_mapping(&samples, max_iterations))
+        } else {
+            None
+        };
+
+        let mut buffer = vec![0u8; (width * height * 3) as usize];
+        buffer.par_chunks_mut(3).enumerate().for_each(|(i, pixel)| {
+            let mu = match &histogram {
+                Some(histogram) => histogram[i],
+                None => samples[i].mu,
+            };
+            pixel.copy_from_slice(&(color_function)(mu));
+        });
+
+        image::save_buffer(path, &buffer, width, height, ColorType::Rgb8)
     }
-    pub fn new() -> Self {
-        Self {
-            set: [0; ((RESOLUTION*RESOLUTION) as usize)],
-            re_limits: [-2.0, 2.0],
-            im_limits: [-2.0, 2.0],
-            max_iterations: 255,
-            color_function: |c: u8| { [c, c, c] }
-        }
-    }
-    /// Asumes 4*RESOLUTION*RESOLUTION size
+    /// Assumes `frame` is `4 * width * height` bytes.
     pub fn draw(self: &MandelbrotSet, frame: &mut [u8]) {
         for (i, pixel) in frame.chunks_exact_mut(4).enumerate() {
-            let c = self.set[i];
-            let rgb = (self.color_function)(c);
+            let mu = if self.use_histogram {
+                self.front_histogram[i]
+            } else {
+                self.front[i].mu
+            };
+            let rgb = (self.color_function)(mu);
             pixel.copy_from_slice(&[rgb[0], rgb[1], rgb[2], 255]);
         }
     }
@@ -87,42 +446,62 @@ impl MandelbrotSet {
 
 fn main() -> Result<(), Error> {
     env_logger::init();
+
+    // Optional `--export-resolution <pixels>` argument controlling the width
+    // of the PNG the `P` keybind produces; defaults to EXPORT_RESOLUTION.
+    let mut export_resolution = EXPORT_RESOLUTION;
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(pos) = args.iter().position(|arg| arg == "--export-resolution") {
+        if let Some(value) = args.get(pos + 1).and_then(|s| s.parse::<u32>().ok()) {
+            export_resolution = value;
+        }
+    }
+
     let event_loop = EventLoop::new();
     let mut input = WinitInputHelper::new();
     let window = {
-        let size = LogicalSize::new(RESOLUTION as f64, RESOLUTION as f64);
+        let size = LogicalSize::new(INITIAL_RESOLUTION as f64, INITIAL_RESOLUTION as f64);
         WindowBuilder::new()
             .with_title("Mandelbrot")
             .with_inner_size(size)
-            .with_min_inner_size(size)
+            .with_min_inner_size(LogicalSize::new(100.0, 100.0))
             .build(&event_loop)
             .unwrap()
     };
 
+    let window_size = window.inner_size();
     let mut pixels = {
-        let window_size = window.inner_size();
         let surface_texture = SurfaceTexture::new(window_size.width, window_size.height, &window);
-        Pixels::new(RESOLUTION as u32, RESOLUTION as u32, surface_texture)?
+        Pixels::new(window_size.width, window_size.height, surface_texture)?
     };
 
-    let mut mandelbrot = MandelbrotSet::new();
-    mandelbrot.calculate();
-    mandelbrot.color_function = |c| {
-        hsv_to_rgb([c, 255, 255])
+    let mut mandelbrot = MandelbrotSet::new(window_size.width, window_size.height);
+    mandelbrot.start_render();
+    mandelbrot.color_function = |mu| {
+        if mu == INTERIOR {
+            return [0, 0, 0];
+        }
+        let hue = ((mu * 8.0) % 255.0) as u8;
+        hsv_to_rgb([hue, 255, 255])
     };
     
-    let mut resize_count = 0;
     event_loop.run(move |event, _, control_flow| {
-        // Draw the current frame
-        if let Event::RedrawRequested(_) = event {
-            if resize_count > 0 {
-                let size = window.inner_size();
-                pixels.resize(size.width, size.height);
-                resize_count -= 1;
+        // Keep polling instead of waiting for a new input event, so a
+        // progressive render pass completing in the background still
+        // triggers a redraw.
+        *control_flow = ControlFlow::Poll;
+
+        // Pick up a freshly completed refinement pass, if any.
+        if let Event::MainEventsCleared = event {
+            if mandelbrot.poll_render() {
+                window.request_redraw();
             }
+        }
 
+        // Draw the current frame
+        if let Event::RedrawRequested(_) = event {
             mandelbrot.draw(pixels.get_frame());
-            
+
             if pixels
                 .render()
                 .map_err(|e| error!("pixels.render() failed: {}", e))
@@ -141,12 +520,23 @@ fn main() -> Result<(), Error> {
                 return;
             }
 
-            // Resize the window
-            //if let Some(size) = input.window_resized() {
-            //    pixels.resize(size.width, size.height);
-            //}
-            // https://github.com/parasyte/pixels/issues/121
-            pixels.resize(window.inner_size().width, window.inner_size().height);
+            // Resize the sampling grid and the pixel buffer to match the
+            // window, so the grid always samples at the window's actual
+            // resolution instead of a fixed-size array.
+            if let Some(size) = input.window_resized() {
+                if size.width > 0 && size.height > 0 {
+                    mandelbrot.resize(size.width, size.height);
+                    let surface_texture = SurfaceTexture::new(size.width, size.height, &window);
+                    match Pixels::new(size.width, size.height, surface_texture) {
+                        Ok(p) => pixels = p,
+                        Err(e) => {
+                            error!("Pixels::new failed: {}", e);
+                            *control_flow = ControlFlow::Exit;
+                            return;
+                        }
+                    }
+                }
+            }
 
             // Mandelbrot movement
             {
@@ -191,7 +581,7 @@ fn main() -> Result<(), Error> {
                 mandelbrot.im_limits[1] -= zoom_dir * shift * im_range;
             }
             if reset {
-                mandelbrot = MandelbrotSet::new();
+                mandelbrot = MandelbrotSet::new(mandelbrot.width, mandelbrot.height);
             }
             let iterations_delta = 10;
             if more_iterations {
@@ -201,8 +591,44 @@ fn main() -> Result<(), Error> {
                 mandelbrot.max_iterations -= 10;
             }
             if up || down || left || right || zoom_in || zoom_out || reset || less_iterations || more_iterations {
-                mandelbrot.calculate();
+                mandelbrot.start_render();
+            }
+            }
+
+            // Mouse-wheel zoom, centered on the point under the cursor
+            // rather than the view center.
+            let scroll_diff = input.scroll_diff();
+            if scroll_diff != 0.0 {
+                if let Some((cursor_x, cursor_y)) = input.cursor() {
+                    let window_size = window.inner_size();
+                    let center = mandelbrot.pixel_to_complex(
+                        cursor_x as f64,
+                        cursor_y as f64,
+                        window_size.width as f64,
+                        window_size.height as f64,
+                    );
+                    let zoom_factor = (-scroll_diff as f64 * 0.1).exp();
+                    mandelbrot.re_limits[0] = center.re + (mandelbrot.re_limits[0] - center.re) * zoom_factor;
+                    mandelbrot.re_limits[1] = center.re + (mandelbrot.re_limits[1] - center.re) * zoom_factor;
+                    mandelbrot.im_limits[0] = center.im + (mandelbrot.im_limits[0] - center.im) * zoom_factor;
+                    mandelbrot.im_limits[1] = center.im + (mandelbrot.im_limits[1] - center.im) * zoom_factor;
+                    mandelbrot.start_render();
+                }
+            }
+
+            // Toggle histogram-equalized coloring.
+            if input.key_pressed(VirtualKeyCode::H) {
+                mandelbrot.toggle_histogram();
             }
+
+            // Export the current view as a PNG, independent of the window's
+            // display resolution.
+            if input.key_pressed(VirtualKeyCode::P) {
+                let path = "mandelbrot.png";
+                match mandelbrot.export_png(export_resolution, path) {
+                    Ok(()) => info!("saved {}", path),
+                    Err(e) => error!("failed to export {}: {}", path, e),
+                }
             }
 
             window.request_redraw();